@@ -1,36 +1,84 @@
+use crate::audio::Clicker;
 use crate::config::Config;
 use crate::fl;
 use cosmic::app::{Command, Core};
 use cosmic::cosmic_config::{self, CosmicConfigEntry};
+use cosmic::iced::keyboard::{key::Named, Key};
 use cosmic::iced::{Alignment, Subscription};
 use cosmic::widget::{self, button, menu, text};
 use cosmic::{cosmic_theme, theme, Application, ApplicationExt, Element};
-use futures_util::SinkExt;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
 
 const REPOSITORY: &str = "https://github.com/jwestall/astronome";
 const APP_ICON: &[u8] = include_bytes!("../res/icons/hicolor/scalable/apps/icon.svg");
 
+/// How many recent taps are kept to average into a tap-tempo reading.
+const TAP_HISTORY: usize = 8;
+/// A gap longer than this since the previous tap starts a fresh reading.
+const TAP_RESET: Duration = Duration::from_secs(2);
+const MIN_TEMPO: u64 = 20;
+const MAX_TEMPO: u64 = 300;
+const MIN_SUBDIVISION: u64 = 1;
+const MAX_SUBDIVISION: u64 = 4;
+
+/// Which value the Up/Down buttons (and their keyboard shortcuts) act on.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mode {
+    Tempo,
+    Beats,
+    Subdivision,
+}
+
+impl Mode {
+    fn next(self) -> Self {
+        match self {
+            Self::Tempo => Self::Beats,
+            Self::Beats => Self::Subdivision,
+            Self::Subdivision => Self::Tempo,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Tempo => "Tempo",
+            Self::Beats => "Beats",
+            Self::Subdivision => "Subdivision",
+        }
+    }
+}
+
 pub struct AppModel {
     core: Core,
     context_page: ContextPage,
     key_binds: HashMap<menu::KeyBind, MenuAction>,
+    config_handler: Option<cosmic_config::Config>,
     config: Config,
-    tempo: u64,
-    beats: u64,
-    is_tempo_mode: bool,
+    mode: Mode,
+    is_playing: bool,
+    current_beat: u64,
+    /// The beat the indicator in `view()` highlights — the one whose click
+    /// last actually sounded, one tick behind `current_beat` which already
+    /// points at what's coming up next.
+    displayed_beat: u64,
+    subdivision_index: u64,
+    is_accent: bool,
+    clicker: Clicker,
+    tap_times: VecDeque<Instant>,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     OpenRepositoryUrl,
-    SubscriptionChannel,
     ToggleContextPage(ContextPage),
     UpdateConfig(Config),
     ButtonUpPressed,
     ButtonDownPressed,
     ButtonModePressed,
     ButtonPlayPressed,
+    ButtonTapPressed,
+    Tick,
+    VolumeChanged(f32),
 }
 
 impl Application for AppModel {
@@ -49,25 +97,35 @@ impl Application for AppModel {
     }
 
     fn init(core: Core, _flags: Self::Flags) -> (Self, Command<Self::Message>) {
+        let config_handler = cosmic_config::Config::new(Self::APP_ID, Config::VERSION).ok();
+        let config = config_handler
+            .as_ref()
+            .map(|context| match Config::get_entry(context) {
+                Ok(config) => config,
+                Err((_errors, config)) => {
+                    // for why in errors {
+                    //     tracing::error!(%why, "error loading app config");
+                    // }
+
+                    config
+                }
+            })
+            .unwrap_or_default();
+
         let mut app = AppModel {
             core,
             context_page: ContextPage::default(),
-            key_binds: HashMap::new(),
-            config: cosmic_config::Config::new(Self::APP_ID, Config::VERSION)
-                .map(|context| match Config::get_entry(&context) {
-                    Ok(config) => config,
-                    Err((_errors, config)) => {
-                        // for why in errors {
-                        //     tracing::error!(%why, "error loading app config");
-                        // }
-
-                        config
-                    }
-                })
-                .unwrap_or_default(),
-            tempo: 120,
-            beats: 4,
-            is_tempo_mode: true,
+            key_binds: key_binds(),
+            config_handler,
+            config,
+            mode: Mode::Tempo,
+            is_playing: false,
+            current_beat: 0,
+            displayed_beat: 0,
+            subdivision_index: 0,
+            is_accent: true,
+            clicker: Clicker::new(),
+            tap_times: VecDeque::with_capacity(TAP_HISTORY),
         };
 
         let command = app.update_title();
@@ -80,7 +138,13 @@ impl Application for AppModel {
             menu::root(fl!("view")),
             menu::items(
                 &self.key_binds,
-                vec![menu::Item::Button(fl!("about"), MenuAction::About)],
+                vec![
+                    menu::Item::Button(fl!("play-pause"), MenuAction::PlayPause),
+                    menu::Item::Button(fl!("increase-value"), MenuAction::IncreaseValue),
+                    menu::Item::Button(fl!("decrease-value"), MenuAction::DecreaseValue),
+                    menu::Item::Button(fl!("toggle-mode"), MenuAction::ToggleMode),
+                    menu::Item::Button(fl!("about"), MenuAction::About),
+                ],
             ),
         )]);
 
@@ -98,19 +162,28 @@ impl Application for AppModel {
     }
 
     fn view(&self) -> Element<Self::Message> {
-        let label = if self.is_tempo_mode {
-            "Tempo"
-        } else {
-            "Beats"
-        };
+        let play_label = if self.is_playing { "Stop" } else { "Play" };
+
+        let beat_indicator = (0..self.config.beats).fold(widget::row(), |row, i| {
+            let dot = if i == self.displayed_beat { "●" } else { "○" };
+            row.push(text(dot))
+        });
 
         let column = widget::column()
-            .push(text(self.beats.to_string()))
-            .push(text(self.tempo.to_string()))
+            .push(beat_indicator)
+            .push(text(self.config.beats.to_string()))
+            .push(text(self.config.tempo.to_string()))
+            .push(text(self.config.subdivision.to_string()))
             .push(widget::row()
-                .push(button::standard(label).on_press(Message::ButtonModePressed))
+                .push(button::standard(self.mode.label()).on_press(Message::ButtonModePressed))
                 .push(button::standard("Up").on_press(Message::ButtonUpPressed))
                 .push(button::standard("Down").on_press(Message::ButtonDownPressed))
+                .push(button::standard(play_label).on_press(Message::ButtonPlayPressed))
+                .push(button::standard("Tap").on_press(Message::ButtonTapPressed))
+            )
+            .push(widget::row()
+                .push(text("Volume"))
+                .push(widget::slider(0.0..=1.0, self.config.volume, Message::VolumeChanged).step(0.01))
             );
 
         column.into()
@@ -122,19 +195,9 @@ impl Application for AppModel {
     /// emit messages to the application through a channel. They are started at the
     /// beginning of the application, and persist through its lifetime.
     fn subscription(&self) -> Subscription<Self::Message> {
-        struct MySubscription;
+        struct TickSubscription;
 
-        Subscription::batch(vec![
-            // Create a subscription which emits updates through a channel.
-            cosmic::iced::subscription::channel(
-                std::any::TypeId::of::<MySubscription>(),
-                4,
-                move |mut channel| async move {
-                    _ = channel.send(Message::SubscriptionChannel).await;
-
-                    futures_util::future::pending().await
-                },
-            ),
+        let mut subscriptions = vec![
             // Watch for application configuration changes.
             self.core()
                 .watch_config::<Config>(Self::APP_ID)
@@ -145,7 +208,38 @@ impl Application for AppModel {
 
                     Message::UpdateConfig(update.config)
                 }),
-        ])
+        ];
+
+        if self.is_playing {
+            let interval = Duration::from_millis(
+                (60_000 / self.config.tempo.max(1)) / self.config.subdivision.max(1),
+            );
+
+            // Re-created whenever `tempo` or `subdivision` changes, so the
+            // new interval takes effect on the very next tick instead of
+            // drifting in at the old one.
+            subscriptions.push(cosmic::iced::subscription::unfold(
+                (
+                    std::any::TypeId::of::<TickSubscription>(),
+                    self.config.tempo,
+                    self.config.subdivision,
+                ),
+                Instant::now() + interval,
+                move |next_tick| async move {
+                    let now = Instant::now();
+                    if next_tick > now {
+                        tokio::time::sleep(next_tick - now).await;
+                    }
+
+                    // Accumulate off the previous deadline rather than `now`,
+                    // so occasional scheduling jitter doesn't compound into
+                    // long-term drift.
+                    (Message::Tick, next_tick + interval)
+                },
+            ));
+        }
+
+        Subscription::batch(subscriptions)
     }
 
     fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
@@ -153,9 +247,6 @@ impl Application for AppModel {
             Message::OpenRepositoryUrl => {
                 _ = open::that_detached(REPOSITORY);
             }
-            Message::SubscriptionChannel => {
-                // For example purposes only.
-            }
             Message::ToggleContextPage(context_page) => {
                 if self.context_page == context_page {
                     // Close the context drawer if the toggled context page is the same.
@@ -172,37 +263,99 @@ impl Application for AppModel {
             Message::UpdateConfig(config) => {
                 self.config = config;
             }
-            Message::ButtonUpPressed => {
-                if self.is_tempo_mode {
-                    self.tempo += 1;
-                } else {
-                    if self.beats < 8 {
-                        self.beats += 1;
-                    } else if self.beats == 8 {
-                        self.beats = 0;
-                    }
+            Message::ButtonUpPressed => match self.mode {
+                Mode::Tempo => {
+                    let tempo = (self.config.tempo + 1).clamp(MIN_TEMPO, MAX_TEMPO);
+                    self.set_tempo(tempo);
                 }
+                Mode::Beats => {
+                    let beats = if self.config.beats < 8 {
+                        self.config.beats + 1
+                    } else {
+                        0
+                    };
+                    self.set_beats(beats);
+                }
+                Mode::Subdivision => {
+                    let subdivision = (self.config.subdivision + 1).clamp(MIN_SUBDIVISION, MAX_SUBDIVISION);
+                    self.set_subdivision(subdivision);
+                }
+            },
+            Message::ButtonDownPressed => match self.mode {
+                Mode::Tempo => {
+                    let tempo = self
+                        .config
+                        .tempo
+                        .saturating_sub(1)
+                        .clamp(MIN_TEMPO, MAX_TEMPO);
+                    self.set_tempo(tempo);
+                }
+                Mode::Beats => {
+                    let beats = if self.config.beats > 0 {
+                        self.config.beats - 1
+                    } else {
+                        8
+                    };
+                    self.set_beats(beats);
+                }
+                Mode::Subdivision => {
+                    let subdivision = self
+                        .config
+                        .subdivision
+                        .saturating_sub(1)
+                        .clamp(MIN_SUBDIVISION, MAX_SUBDIVISION);
+                    self.set_subdivision(subdivision);
+                }
+            },
+            Message::ButtonModePressed => {
+                self.mode = self.mode.next();
             }
-            Message::ButtonDownPressed => {
-                if self.is_tempo_mode {
-                    self.tempo -= 1;
-                } else {
-                    if self.beats > 0 {
-                        self.beats -= 1;
-                    } else if self.beats == 0 {
-                        self.beats = 8;
+            Message::ButtonPlayPressed => {
+                self.is_playing = !self.is_playing;
+                if self.is_playing {
+                    self.current_beat = 0;
+                    self.displayed_beat = 0;
+                    self.subdivision_index = 0;
+                    self.is_accent = true;
+                }
+            }
+            Message::ButtonTapPressed => {
+                let now = Instant::now();
+
+                if matches!(self.tap_times.back(), Some(previous) if now - *previous > TAP_RESET) {
+                    self.tap_times.clear();
+                }
+
+                if self.tap_times.len() == TAP_HISTORY {
+                    self.tap_times.pop_front();
+                }
+                self.tap_times.push_back(now);
+
+                if self.tap_times.len() >= 2 {
+                    let first = *self.tap_times.front().unwrap();
+                    let last = *self.tap_times.back().unwrap();
+                    let gaps = self.tap_times.len() as u32 - 1;
+                    let avg_interval_ms = (last - first).as_millis() as u64 / u64::from(gaps);
+
+                    if avg_interval_ms > 0 {
+                        let tempo = (60_000 / avg_interval_ms).clamp(MIN_TEMPO, MAX_TEMPO);
+                        self.set_tempo(tempo);
                     }
                 }
             }
-            Message::ButtonModePressed => {
-                if self.is_tempo_mode {
-                    self.is_tempo_mode = false;
-                } else {
-                    self.is_tempo_mode = true;
+            Message::Tick => {
+                self.is_accent = self.subdivision_index == 0 && self.current_beat == 0;
+                self.displayed_beat = self.current_beat;
+                self.clicker.click(self.is_accent, self.config.volume);
+
+                self.subdivision_index += 1;
+                if self.subdivision_index >= self.config.subdivision.max(1) {
+                    self.subdivision_index = 0;
+                    self.current_beat = (self.current_beat + 1) % self.config.beats.max(1);
                 }
             }
-            Message::ButtonPlayPressed => {
-
+            Message::VolumeChanged(volume) => {
+                self.set_volume(volume);
             }
         }
         Command::none()
@@ -237,6 +390,42 @@ impl AppModel {
 
         self.set_window_title(window_title)
     }
+
+    /// Writes a new tempo through to the config, which both updates
+    /// `self.config` in place and persists the change to disk. Falls back
+    /// to updating `self.config` directly if there is no config handler to
+    /// persist through, so controls keep working in-memory even when
+    /// `cosmic_config::Config::new` failed at startup.
+    fn set_tempo(&mut self, tempo: u64) {
+        match self.config_handler {
+            Some(ref handler) => _ = self.config.set_tempo(handler, tempo),
+            None => self.config.tempo = tempo,
+        }
+    }
+
+    /// Writes a new beat count through to the config.
+    fn set_beats(&mut self, beats: u64) {
+        match self.config_handler {
+            Some(ref handler) => _ = self.config.set_beats(handler, beats),
+            None => self.config.beats = beats,
+        }
+    }
+
+    /// Writes a new volume through to the config.
+    fn set_volume(&mut self, volume: f32) {
+        match self.config_handler {
+            Some(ref handler) => _ = self.config.set_volume(handler, volume),
+            None => self.config.volume = volume,
+        }
+    }
+
+    /// Writes a new subdivision through to the config.
+    fn set_subdivision(&mut self, subdivision: u64) {
+        match self.config_handler {
+            Some(ref handler) => _ = self.config.set_subdivision(handler, subdivision),
+            None => self.config.subdivision = subdivision,
+        }
+    }
 }
 
 /// The context page to display in the context drawer.
@@ -257,6 +446,10 @@ impl ContextPage {
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum MenuAction {
     About,
+    PlayPause,
+    IncreaseValue,
+    DecreaseValue,
+    ToggleMode,
 }
 
 impl menu::action::MenuAction for MenuAction {
@@ -265,6 +458,47 @@ impl menu::action::MenuAction for MenuAction {
     fn message(&self) -> Self::Message {
         match self {
             MenuAction::About => Message::ToggleContextPage(ContextPage::About),
+            MenuAction::PlayPause => Message::ButtonPlayPressed,
+            MenuAction::IncreaseValue => Message::ButtonUpPressed,
+            MenuAction::DecreaseValue => Message::ButtonDownPressed,
+            MenuAction::ToggleMode => Message::ButtonModePressed,
         }
     }
 }
+
+/// Keyboard shortcuts for hands-free transport and tempo control while
+/// practicing an instrument.
+fn key_binds() -> HashMap<menu::KeyBind, MenuAction> {
+    let mut key_binds = HashMap::new();
+
+    key_binds.insert(
+        menu::KeyBind {
+            modifiers: Vec::new(),
+            key: Key::Named(Named::Space),
+        },
+        MenuAction::PlayPause,
+    );
+    key_binds.insert(
+        menu::KeyBind {
+            modifiers: Vec::new(),
+            key: Key::Named(Named::ArrowUp),
+        },
+        MenuAction::IncreaseValue,
+    );
+    key_binds.insert(
+        menu::KeyBind {
+            modifiers: Vec::new(),
+            key: Key::Named(Named::ArrowDown),
+        },
+        MenuAction::DecreaseValue,
+    );
+    key_binds.insert(
+        menu::KeyBind {
+            modifiers: Vec::new(),
+            key: Key::Named(Named::Tab),
+        },
+        MenuAction::ToggleMode,
+    );
+
+    key_binds
+}
@@ -0,0 +1,79 @@
+//! Click-track playback.
+//!
+//! Clicks are synthesized in-process as short sine bursts rather than
+//! bundled as wav assets, and pushed into a `rodio::Sink` that runs on its
+//! own output stream so audio latency stays independent of the iced
+//! runtime.
+
+use rodio::buffer::SamplesBuffer;
+use rodio::{OutputStream, Sink};
+
+const SAMPLE_RATE: u32 = 48_000;
+const CLICK_DURATION_MS: u32 = 30;
+const ACCENT_FREQUENCY_HZ: f32 = 1000.0;
+const REGULAR_FREQUENCY_HZ: f32 = 800.0;
+
+/// Owns the audio output device and plays metronome clicks on demand.
+///
+/// Kept on `AppModel` rather than `Core` since it is application-specific
+/// playback state, not shared cosmic runtime state. Opening an output
+/// device is fallible (headless/container environments, no audio session
+/// yet, etc.), so `click()` silently no-ops rather than the app panicking
+/// at startup over missing audio.
+pub struct Clicker {
+    // Kept alive alongside `sink` even though never read directly: dropping
+    // it tears down the output stream.
+    _stream: Option<OutputStream>,
+    sink: Option<Sink>,
+}
+
+impl Clicker {
+    pub fn new() -> Self {
+        let (stream, sink) = match OutputStream::try_default() {
+            Ok((stream, handle)) => match Sink::try_new(&handle) {
+                Ok(sink) => (Some(stream), Some(sink)),
+                Err(_) => (Some(stream), None),
+            },
+            Err(_) => (None, None),
+        };
+
+        Self {
+            _stream: stream,
+            sink,
+        }
+    }
+
+    /// Plays a single click, pitched for whether this is the accented first
+    /// beat of the bar; accented and regular clicks play at the same
+    /// `volume`. Does nothing if no audio output is available.
+    pub fn click(&self, is_accent: bool, volume: f32) {
+        let Some(ref sink) = self.sink else {
+            return;
+        };
+
+        let frequency = if is_accent {
+            ACCENT_FREQUENCY_HZ
+        } else {
+            REGULAR_FREQUENCY_HZ
+        };
+
+        sink.set_volume(volume.clamp(0.0, 1.0));
+        sink.append(click_buffer(frequency));
+    }
+}
+
+/// Generates a short sine burst with a fast linear decay envelope so the
+/// click starts and ends without popping.
+fn click_buffer(frequency: f32) -> SamplesBuffer<f32> {
+    let sample_count = (SAMPLE_RATE * CLICK_DURATION_MS / 1000) as usize;
+
+    let samples: Vec<f32> = (0..sample_count)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let envelope = 1.0 - (i as f32 / sample_count as f32);
+            (2.0 * std::f32::consts::PI * frequency * t).sin() * envelope
+        })
+        .collect();
+
+    SamplesBuffer::new(1, SAMPLE_RATE, samples)
+}
@@ -0,0 +1,34 @@
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
+/// Persisted application settings, backed by `cosmic_config`.
+///
+/// `AppModel` holds one of these as its single source of truth for tempo,
+/// beats, volume and subdivision: writes go through the `set_*` accessors
+/// generated by `CosmicConfigEntry`, which both update this struct in place
+/// and persist the change to disk, and `watch_config` feeds any on-disk
+/// change (from this process or another) back in through
+/// `Message::UpdateConfig`.
+#[derive(Clone, CosmicConfigEntry, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Config {
+    pub tempo: u64,
+    pub beats: u64,
+    pub volume: f32,
+    /// Clicks per beat: 1 (quarter), 2 (eighth), 3 (triplet), 4 (sixteenth).
+    pub subdivision: u64,
+}
+
+impl Config {
+    pub const VERSION: u64 = 1;
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            tempo: 120,
+            beats: 4,
+            volume: 0.8,
+            subdivision: 1,
+        }
+    }
+}